@@ -0,0 +1,10 @@
+//! Generates the `prost` types for `encoding::protobuf` from the OpenMetrics
+//! proto definition vendored in `proto/`.
+
+fn main() {
+    prost_build::compile_protos(
+        &["proto/openmetrics_data_model.proto"],
+        &["proto/"],
+    )
+    .expect("failed to compile OpenMetrics protobuf definitions");
+}
@@ -0,0 +1,158 @@
+//! Derive macros for `open_metrics_client`'s `encoding::text::Encode` trait.
+//!
+//! `open_metrics_client` does not re-export them: import the
+//! `EncodeLabelSet` and `EncodeLabelValue` derive macros from this crate.
+
+use proc_macro::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DataEnum, DataStruct, DeriveInput, Fields};
+
+/// Derives `open_metrics_client::encoding::text::Encode` for a struct,
+/// emitting one `name="value"` pair per named field, separated by commas,
+/// with each value delegated to its own
+/// `open_metrics_client::encoding::text::Encode` implementation.
+#[proc_macro_derive(EncodeLabelSet)]
+pub fn derive_encode_label_set(input: TokenStream) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let fields = match data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => fields.named,
+        _ => {
+            return syn::Error::new(
+                ident.span(),
+                "EncodeLabelSet can only be derived for structs with named fields.",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+
+    let field_count = fields.len();
+    let encode_fields = fields.iter().enumerate().map(|(i, f)| {
+        let name = f.ident.as_ref().unwrap();
+        let name_str = name.to_string();
+        let separator = if i + 1 < field_count {
+            quote_spanned! { f.span() => writer.write_all(b",")?; }
+        } else {
+            quote! {}
+        };
+
+        quote_spanned! { f.span() =>
+            writer.write_all(#name_str.as_bytes())?;
+            writer.write_all(b"=\"")?;
+            open_metrics_client::encoding::text::Encode::encode(&self.#name, writer)?;
+            writer.write_all(b"\"")?;
+            #separator
+        }
+    });
+
+    let stream = if field_count == 0 {
+        quote! {
+            impl open_metrics_client::encoding::text::Encode for #ident {
+                fn encode(&self, _writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+                    Ok(())
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl open_metrics_client::encoding::text::Encode for #ident {
+                fn encode(&self, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+                    #(#encode_fields)*
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    stream.into()
+}
+
+/// Derives `open_metrics_client::encoding::text::Encode` for an enum,
+/// mapping each unit variant to its identifier string and each single-field
+/// tuple variant to its field's `Display` output.
+#[proc_macro_derive(EncodeLabelValue)]
+pub fn derive_encode_label_value(input: TokenStream) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let variants = match data {
+        Data::Enum(DataEnum { variants, .. }) => variants,
+        _ => {
+            return syn::Error::new(
+                ident.span(),
+                "EncodeLabelValue can only be derived for enums.",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+
+    let mut match_arms = Vec::with_capacity(variants.len());
+    for v in &variants {
+        let variant = &v.ident;
+
+        let arm = match &v.fields {
+            Fields::Unit => {
+                let variant_str = escape(&variant.to_string());
+                quote_spanned! { v.span() =>
+                    #ident::#variant => writer.write_all(#variant_str.as_bytes()),
+                }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                quote_spanned! { v.span() =>
+                    #ident::#variant(value) => {
+                        let rendered = ::std::string::ToString::to_string(value)
+                            .replace('\\', "\\\\")
+                            .replace('"', "\\\"");
+                        writer.write_all(rendered.as_bytes())
+                    }
+                }
+            }
+            _ => {
+                return syn::Error::new(
+                    v.span(),
+                    "EncodeLabelValue can only be derived for enums whose variants are unit \
+                     variants or single-field tuple variants implementing Display.",
+                )
+                .to_compile_error()
+                .into()
+            }
+        };
+
+        match_arms.push(arm);
+    }
+
+    let stream = quote! {
+        impl open_metrics_client::encoding::text::Encode for #ident {
+            fn encode(&self, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+                match self {
+                    #(#match_arms)*
+                }
+            }
+        }
+    };
+
+    stream.into()
+}
+
+/// Escapes quotes and backslashes in a label value at expansion time, the
+/// same way the generated code escapes `Display`-backed values at runtime.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_quotes_and_backslashes() {
+        assert_eq!(r#"a\"b"#, escape(r#"a"b"#));
+        assert_eq!(r#"a\\b"#, escape(r"a\b"));
+        assert_eq!("plain", escape("plain"));
+    }
+}
@@ -0,0 +1,70 @@
+//! Tests exercising the `EncodeLabelSet`/`EncodeLabelValue`
+//! derives end to end against a multi-field struct and an enum.
+
+use open_metrics_client::encoding::text::Encode;
+use open_metrics_client::metrics::counter::{Atomic, Counter};
+use open_metrics_client::metrics::family::Family;
+use open_metrics_client::registry::Registry;
+use open_metrics_client_derive_text_encode::{EncodeLabelSet, EncodeLabelValue};
+use std::sync::atomic::AtomicU64;
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Method {
+    Get,
+    Put,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum StatusClass {
+    Success,
+    Error(u16),
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct Labels {
+    method: Method,
+    status: StatusClass,
+}
+
+fn encode<T: Encode>(value: &T) -> String {
+    let mut out = vec![];
+    value.encode(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn encodes_multi_field_struct() {
+    let labels = Labels {
+        method: Method::Get,
+        status: StatusClass::Success,
+    };
+
+    assert_eq!("method=\"Get\",status=\"Success\"", encode(&labels));
+}
+
+#[test]
+fn encodes_display_backed_variant() {
+    let labels = Labels {
+        method: Method::Put,
+        status: StatusClass::Error(503),
+    };
+
+    assert_eq!("method=\"Put\",status=\"503\"", encode(&labels));
+}
+
+/// A derived label type only implements `encoding::text::Encode`, not
+/// `encoding::protobuf::EncodeLabelSet`, so it must still be registrable
+/// through the text-only [`Registry::register`].
+#[test]
+fn derived_label_set_is_registrable() {
+    let mut registry = Registry::default();
+    let family = Family::<Labels, Counter<AtomicU64>>::default();
+    registry.register("requests", "Count of requests", family.clone());
+
+    family
+        .get_or_create(&Labels {
+            method: Method::Get,
+            status: StatusClass::Success,
+        })
+        .inc();
+}
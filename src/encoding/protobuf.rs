@@ -0,0 +1,220 @@
+//! Open Metrics protobuf encoding.
+//!
+//! This module is the binary sibling of [`text`](crate::encoding::text): it
+//! serializes a [`Registry`](crate::registry::Registry) into the OpenMetrics
+//! `MetricSet` protobuf message instead of the text exposition format.
+//! Scrapers and push gateways that prefer the binary format can use
+//! [`encode`] in place of [`text::encode`](crate::encoding::text::encode).
+
+use crate::metrics::counter::{self, Counter};
+use crate::metrics::family::Family;
+use crate::metrics::gauge::{self, Gauge};
+use crate::metrics::histogram::Histogram;
+use crate::metrics::{MetricType, TypedMetric};
+use crate::registry::Registry;
+
+/// Types generated by `prost` from `proto/openmetrics_data_model.proto`.
+pub mod openmetrics {
+    include!(concat!(env!("OUT_DIR"), "/openmetrics.rs"));
+}
+
+use openmetrics::metric_point::Value as ProtoValue;
+use openmetrics::{
+    counter_value, histogram_value, CounterValue, GaugeValue, HistogramValue, Label, Metric,
+    MetricFamily, MetricPoint, MetricSet,
+};
+
+/// Encode the metrics registered with `registry` through
+/// [`Registry::register_protobuf`] into the OpenMetrics protobuf `MetricSet`
+/// message, returning its serialized bytes. Metrics added with
+/// [`Registry::register`] are not included.
+pub fn encode(registry: &Registry) -> Vec<u8> {
+    let mut metric_set = MetricSet::default();
+
+    for (descriptor, metric) in registry.iter_protobuf() {
+        metric_set.metric_families.push(MetricFamily {
+            name: descriptor.name().to_string(),
+            help: descriptor.help().to_string(),
+            r#type: proto_type(metric.metric_type()) as i32,
+            unit: String::new(),
+            metrics: metric.encode_metrics(),
+        });
+    }
+
+    prost::Message::encode_to_vec(&metric_set)
+}
+
+fn proto_type(t: MetricType) -> openmetrics::MetricType {
+    match t {
+        MetricType::Counter => openmetrics::MetricType::Counter,
+        MetricType::Gauge => openmetrics::MetricType::Gauge,
+        MetricType::Histogram => openmetrics::MetricType::Histogram,
+        MetricType::Unknown => openmetrics::MetricType::Unknown,
+    }
+}
+
+/// Protobuf counterpart to [`text::Encode`](crate::encoding::text::Encode).
+///
+/// Implemented by every metric type (and by [`Family`]) so that anything
+/// registered in a [`Registry`] can produce the `repeated Metric` entries of
+/// its `MetricFamily`.
+pub trait EncodeMetric {
+    /// Encode the metric, or each of a family's series, into the `Metric`
+    /// messages that make up a `MetricFamily`.
+    fn encode_metrics(&self) -> Vec<Metric>;
+
+    /// The Open Metrics metric type, used to populate `MetricFamily::type`.
+    fn metric_type(&self) -> MetricType;
+}
+
+/// Protobuf counterpart to a label set's `encoding::text::Encode`
+/// implementation: produces the `repeated Label` entries of a `Metric`.
+pub trait EncodeLabelSet {
+    /// Encode `self` as protobuf `Label` messages.
+    fn encode_labels(&self) -> Vec<Label>;
+}
+
+impl EncodeLabelSet for Vec<(String, String)> {
+    fn encode_labels(&self) -> Vec<Label> {
+        self.iter()
+            .map(|(name, value)| Label {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<S, M> EncodeMetric for Family<S, M>
+where
+    S: EncodeLabelSet + Clone + std::hash::Hash + Eq,
+    M: EncodeMetric + TypedMetric,
+{
+    fn encode_metrics(&self) -> Vec<Metric> {
+        // Cross-shard ordering is unspecified; unlike the text encoder this
+        // format has no line-based determinism to preserve, so it is left
+        // as-is rather than sorted.
+        self.read()
+            .iter()
+            .flat_map(|shard| shard.iter())
+            .flat_map(|(label_set, metric)| {
+                let labels = label_set.encode_labels();
+                metric.encode_metrics().into_iter().map(move |mut m| {
+                    m.labels = labels.clone();
+                    m
+                })
+            })
+            .collect()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        <M as TypedMetric>::TYPE
+    }
+}
+
+/// Converts a counter's stored number into the `CounterValue` oneof variant
+/// that matches its type, so an integer-backed counter encodes as
+/// `int_value` and a float-backed one as `double_value`.
+trait IntoCounterTotal {
+    fn into_counter_total(self) -> counter_value::Total;
+}
+
+impl IntoCounterTotal for u64 {
+    fn into_counter_total(self) -> counter_value::Total {
+        counter_value::Total::IntValue(self)
+    }
+}
+
+impl IntoCounterTotal for f64 {
+    fn into_counter_total(self) -> counter_value::Total {
+        counter_value::Total::DoubleValue(self)
+    }
+}
+
+/// Converts a gauge's stored number into the `GaugeValue` oneof variant that
+/// matches its type, so an integer-backed gauge encodes as `int_value` and a
+/// float-backed one as `double_value`.
+trait IntoGaugeValue {
+    fn into_gauge_value(self) -> openmetrics::gauge_value::Value;
+}
+
+impl IntoGaugeValue for i64 {
+    fn into_gauge_value(self) -> openmetrics::gauge_value::Value {
+        openmetrics::gauge_value::Value::IntValue(self)
+    }
+}
+
+impl IntoGaugeValue for f64 {
+    fn into_gauge_value(self) -> openmetrics::gauge_value::Value {
+        openmetrics::gauge_value::Value::DoubleValue(self)
+    }
+}
+
+impl<A> EncodeMetric for Counter<A>
+where
+    A: counter::Atomic,
+    A::Number: IntoCounterTotal,
+{
+    fn encode_metrics(&self) -> Vec<Metric> {
+        vec![Metric {
+            labels: vec![],
+            metric_points: vec![MetricPoint {
+                value: Some(ProtoValue::CounterValue(CounterValue {
+                    total: Some(self.get().into_counter_total()),
+                })),
+            }],
+        }]
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+}
+
+impl<A> EncodeMetric for Gauge<A>
+where
+    A: gauge::Atomic,
+    A::Number: IntoGaugeValue,
+{
+    fn encode_metrics(&self) -> Vec<Metric> {
+        vec![Metric {
+            labels: vec![],
+            metric_points: vec![MetricPoint {
+                value: Some(ProtoValue::GaugeValue(GaugeValue {
+                    value: Some(self.get().into_gauge_value()),
+                })),
+            }],
+        }]
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+}
+
+impl EncodeMetric for Histogram {
+    fn encode_metrics(&self) -> Vec<Metric> {
+        let (sum, count, buckets) = self.get();
+
+        vec![Metric {
+            labels: vec![],
+            metric_points: vec![MetricPoint {
+                value: Some(ProtoValue::HistogramValue(HistogramValue {
+                    sum: Some(histogram_value::Sum::DoubleValue(sum)),
+                    count,
+                    buckets: buckets
+                        .iter()
+                        .map(|(upper_bound, count)| histogram_value::Bucket {
+                            count: *count,
+                            upper_bound: *upper_bound,
+                        })
+                        .collect(),
+                })),
+            }],
+        }]
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+}
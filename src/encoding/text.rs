@@ -0,0 +1,186 @@
+//! Open Metrics text encoding.
+//!
+//! This module is the text sibling of
+//! [`protobuf`](crate::encoding::protobuf): it serializes a
+//! [`Registry`](crate::registry::Registry) into the OpenMetrics text
+//! exposition format.
+
+use crate::metrics::counter::{self, Counter};
+use crate::metrics::family::Family;
+use crate::metrics::gauge::{self, Gauge};
+use crate::metrics::histogram::Histogram;
+use crate::metrics::{MetricType, TypedMetric};
+use crate::registry::Registry;
+use std::io::Write;
+
+/// Encodes a label set or label value the way the text exposition format
+/// represents it, e.g. a `Labels { method: Method::Get }` encodes as
+/// `method="Get"`.
+///
+/// Implement this for a custom label type to avoid the overhead of the
+/// `Vec<(String, String)>` convenience type, or derive it with
+/// `#[derive(EncodeLabelSet)]`/`#[derive(EncodeLabelValue)]` (defined in
+/// the `open_metrics_client_derive_text_encode` crate).
+pub trait Encode {
+    /// Encode `self`, without surrounding braces, to `writer`.
+    fn encode(&self, writer: &mut dyn Write) -> std::io::Result<()>;
+}
+
+impl Encode for Vec<(String, String)> {
+    fn encode(&self, writer: &mut dyn Write) -> std::io::Result<()> {
+        let mut iter = self.iter().peekable();
+        while let Some((name, value)) = iter.next() {
+            write!(writer, "{}=\"{}\"", name, value)?;
+            if iter.peek().is_some() {
+                writer.write_all(b",")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Text counterpart to
+/// [`protobuf::EncodeMetric`](crate::encoding::protobuf::EncodeMetric).
+///
+/// Implemented by every metric type (and by [`Family`]) so that anything
+/// registered in a [`Registry`] can write its OpenMetrics sample line(s).
+pub(crate) trait EncodeMetric {
+    /// Write this metric's sample line(s) to `writer`. `name` is the metric
+    /// family name; `labels` is the already-encoded label set, without
+    /// surrounding braces, or the empty string when there are none.
+    fn encode(&self, name: &str, labels: &str, writer: &mut dyn Write) -> std::io::Result<()>;
+
+    /// The Open Metrics metric type, used to populate the `# TYPE` line.
+    fn metric_type(&self) -> MetricType;
+}
+
+/// Encode the metrics registered with `registry` into the OpenMetrics text
+/// exposition format.
+pub fn encode(writer: &mut impl Write, registry: &Registry) -> std::io::Result<()> {
+    for (descriptor, metric) in registry.iter() {
+        let name = descriptor.name();
+        let help = descriptor.help();
+        let help = if help.ends_with('.') {
+            help.to_owned()
+        } else {
+            format!("{}.", help)
+        };
+
+        writeln!(writer, "# HELP {} {}", name, help)?;
+        writeln!(writer, "# TYPE {} {}", name, type_str(metric.metric_type()))?;
+        metric.encode(name, "", writer)?;
+    }
+
+    writer.write_all(b"# EOF\n")
+}
+
+fn type_str(t: MetricType) -> &'static str {
+    match t {
+        MetricType::Counter => "counter",
+        MetricType::Gauge => "gauge",
+        MetricType::Histogram => "histogram",
+        MetricType::Unknown => "unknown",
+    }
+}
+
+impl<S, M> EncodeMetric for Family<S, M>
+where
+    S: Encode + Clone + std::hash::Hash + Eq,
+    M: EncodeMetric + TypedMetric,
+{
+    fn encode(&self, name: &str, _labels: &str, writer: &mut dyn Write) -> std::io::Result<()> {
+        // Buffer each series' fully rendered sample line(s) and sort the
+        // buffers before writing them out. Cross-shard ordering is
+        // unspecified, and within a shard the backing `HashMap`'s iteration
+        // order is unspecified too, so this is the only way to get
+        // deterministic output without requiring `S: Ord`.
+        let mut lines = Vec::new();
+        for shard in self.read().iter() {
+            for (label_set, metric) in shard.iter() {
+                let mut labels = Vec::new();
+                label_set.encode(&mut labels)?;
+                let labels = String::from_utf8(labels).expect("label encoding to be valid utf8");
+
+                let mut line = Vec::new();
+                metric.encode(name, &labels, &mut line)?;
+                lines.push(line);
+            }
+        }
+        lines.sort();
+
+        for line in lines {
+            writer.write_all(&line)?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        <M as TypedMetric>::TYPE
+    }
+}
+
+impl<A> EncodeMetric for Counter<A>
+where
+    A: counter::Atomic,
+    A::Number: std::fmt::Display,
+{
+    fn encode(&self, name: &str, labels: &str, writer: &mut dyn Write) -> std::io::Result<()> {
+        if labels.is_empty() {
+            writeln!(writer, "{}_total {}", name, self.get())
+        } else {
+            writeln!(writer, "{}_total{{{}}} {}", name, labels, self.get())
+        }
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+}
+
+impl<A> EncodeMetric for Gauge<A>
+where
+    A: gauge::Atomic,
+    A::Number: std::fmt::Display,
+{
+    fn encode(&self, name: &str, labels: &str, writer: &mut dyn Write) -> std::io::Result<()> {
+        if labels.is_empty() {
+            writeln!(writer, "{} {}", name, self.get())
+        } else {
+            writeln!(writer, "{}{{{}}} {}", name, labels, self.get())
+        }
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+}
+
+impl EncodeMetric for Histogram {
+    fn encode(&self, name: &str, labels: &str, writer: &mut dyn Write) -> std::io::Result<()> {
+        let (sum, count, buckets) = self.get();
+
+        for (upper_bound, bucket_count) in &buckets {
+            let le = format!("le=\"{}\"", upper_bound);
+            if labels.is_empty() {
+                writeln!(writer, "{}_bucket{{{}}} {}", name, le, bucket_count)?;
+            } else {
+                writeln!(writer, "{}_bucket{{{},{}}} {}", name, labels, le, bucket_count)?;
+            }
+        }
+
+        if labels.is_empty() {
+            writeln!(writer, "{}_sum {}", name, sum)?;
+            writeln!(writer, "{}_count {}", name, count)?;
+        } else {
+            writeln!(writer, "{}_sum{{{}}} {}", name, labels, sum)?;
+            writeln!(writer, "{}_count{{{}}} {}", name, labels, count)?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+}
@@ -3,9 +3,67 @@
 //! See [`Family`] for details.
 
 use super::{MetricType, TypedMetric};
-use owning_ref::OwningRef;
+use owning_ref::{OwningRef, StableAddress};
+use parking_lot::{RwLock, RwLockWriteGuard};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Wraps a [`parking_lot::RwLockReadGuard`] so it can be used as an
+/// [`OwningRef`] owner.
+///
+/// `owning_ref` only ships a [`StableAddress`] impl for `std::sync`'s guard
+/// types, not `parking_lot`'s, even though the same guarantee holds:
+/// `parking_lot`'s guard holds a pointer into the lock rather than the data
+/// itself, so the address it dereferences to does not move when the guard
+/// is moved.
+struct RwLockReadGuard<'a, T>(parking_lot::RwLockReadGuard<'a, T>);
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+unsafe impl<'a, T> StableAddress for RwLockReadGuard<'a, T> {}
+
+/// Number of shards the backing map of a [`Family`] is split into.
+///
+/// Each shard is guarded by its own [`RwLock`], so a write lock taken while
+/// inserting a label set seen for the first time only contends the other
+/// threads hashing into the same shard, not the whole family.
+const SHARD_COUNT: usize = 16;
+
+/// A non-cryptographic, allocation-free hasher (FNV-1a) used only to pick a
+/// shard. Unlike the [`Hash`] implementation callers provide for `S`, which
+/// feeds [`HashMap`]'s own `SipHash`, this one is optimized purely for
+/// picking a shard index quickly, not for collision resistance.
+struct ShardHasher(u64);
+
+impl Default for ShardHasher {
+    fn default() -> Self {
+        // FNV-1a offset basis.
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for ShardHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut hash = self.0;
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        self.0 = hash;
+    }
+}
 
 /// Representation of the OpenMetrics *MetricFamily* data type.
 ///
@@ -110,7 +168,7 @@ use std::sync::{Arc, RwLock, RwLockReadGuard};
 /// # assert_eq!(expected, String::from_utf8(buffer).unwrap());
 /// ```
 pub struct Family<S, M> {
-    metrics: Arc<RwLock<HashMap<S, M>>>,
+    shards: Arc<[RwLock<HashMap<S, M>>; SHARD_COUNT]>,
     /// Function that when called constructs a new metric.
     ///
     /// For most metric types this would simply be its [`Default`]
@@ -119,42 +177,64 @@ pub struct Family<S, M> {
     /// [`Histogram`](crate::metrics::histogram::Histogram) in order to set
     /// specific buckets, a custom constructor is set via
     /// [`Family::new_with_constructor`].
-    constructor: fn() -> M,
+    constructor: Arc<dyn Fn() -> M + Send + Sync>,
 }
 
 impl<S: Clone + std::hash::Hash + Eq, M: Default> Default for Family<S, M> {
     fn default() -> Self {
         Self {
-            metrics: Arc::new(RwLock::new(Default::default())),
-            constructor: M::default,
+            shards: Arc::new(std::array::from_fn(|_| RwLock::new(HashMap::new()))),
+            constructor: Arc::new(M::default),
         }
     }
 }
 
 impl<S: Clone + std::hash::Hash + Eq, M> Family<S, M> {
-    pub fn new_with_constructor(constructor: fn() -> M) -> Self {
+    /// Create a new [`Family`] with a custom constructor for each new metric
+    /// created for a given label set.
+    ///
+    /// The constructor may capture state, e.g. to build
+    /// [`Histogram`](crate::metrics::histogram::Histogram)s whose bucket
+    /// boundaries come from runtime configuration shared across the family.
+    pub fn new_with_constructor(constructor: impl Fn() -> M + Send + Sync + 'static) -> Self {
         Self {
-            metrics: Arc::new(RwLock::new(Default::default())),
-            constructor,
+            shards: Arc::new(std::array::from_fn(|_| RwLock::new(HashMap::new()))),
+            constructor: Arc::new(constructor),
         }
     }
 }
 
 impl<S: Clone + std::hash::Hash + Eq, M> Family<S, M> {
+    /// Hash `sample_set` to pick the shard it lives in.
+    fn shard_for(&self, sample_set: &S) -> &RwLock<HashMap<S, M>> {
+        let mut hasher = ShardHasher::default();
+        sample_set.hash(&mut hasher);
+        let index = (hasher.finish() as usize) % self.shards.len();
+        &self.shards[index]
+    }
+
     pub fn get_or_create(&self, sample_set: &S) -> OwningRef<RwLockReadGuard<HashMap<S, M>>, M> {
-        let read_guard = self.metrics.read().unwrap();
+        let shard = self.shard_for(sample_set);
+
+        let read_guard = RwLockReadGuard(shard.read());
         if let Ok(metric) =
             OwningRef::new(read_guard).try_map(|metrics| metrics.get(sample_set).ok_or(()))
         {
             return metric;
         }
 
-        let mut write_guard = self.metrics.write().unwrap();
-        write_guard.insert(sample_set.clone(), (self.constructor)());
-
-        drop(write_guard);
+        let mut write_guard = shard.write();
+        write_guard
+            .entry(sample_set.clone())
+            .or_insert_with(|| (self.constructor)());
 
-        let read_guard = self.metrics.read().unwrap();
+        // Downgrade rather than dropping the write guard and re-acquiring a
+        // read lock: now that `Family::remove`/`Family::clear` exist,
+        // another thread could delete the entry just inserted above in that
+        // gap, turning the `expect` below into a reachable panic on valid
+        // input. Downgrading keeps the lock held across the transition so
+        // no such window exists.
+        let read_guard = RwLockReadGuard(RwLockWriteGuard::downgrade(write_guard));
         OwningRef::new(read_guard).map(|metrics| {
             metrics
                 .get(sample_set)
@@ -162,16 +242,59 @@ impl<S: Clone + std::hash::Hash + Eq, M> Family<S, M> {
         })
     }
 
-    pub(crate) fn read(&self) -> RwLockReadGuard<HashMap<S, M>> {
-        self.metrics.read().unwrap()
+    /// Look up a metric by its label set, returning `None` if it has not
+    /// been created yet.
+    ///
+    /// Unlike [`Family::get_or_create`], this never inserts into the
+    /// backing map.
+    pub fn get(&self, labels: &S) -> Option<OwningRef<RwLockReadGuard<HashMap<S, M>>, M>> {
+        let read_guard = RwLockReadGuard(self.shard_for(labels).read());
+        OwningRef::new(read_guard)
+            .try_map(|metrics| metrics.get(labels).ok_or(()))
+            .ok()
+    }
+
+    /// Remove the metric for the given label set, returning `true` if a
+    /// metric was present and removed.
+    ///
+    /// This bounds the memory use of a [`Family`] whose label values come
+    /// from high-cardinality or ephemeral sources by letting callers drop
+    /// series that will never be observed again.
+    pub fn remove(&self, labels: &S) -> bool {
+        self.shard_for(labels).write().remove(labels).is_some()
+    }
+
+    /// Remove all metrics from the family.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().clear()
+        }
+    }
+
+    /// Acquire a read lock on every shard, in a fixed order to avoid
+    /// deadlocking against a concurrent [`Family::clear`].
+    ///
+    /// Used by the encoders to iterate over all metrics in the family. The
+    /// order in which label sets are yielded across shards is unspecified;
+    /// callers that need deterministic output (e.g. the text encoder) must
+    /// sort it themselves.
+    ///
+    /// This keeps the name `read` that the single-shard implementation used,
+    /// even though sharding means it now returns one guard per shard rather
+    /// than a single guard.
+    pub(crate) fn read(&self) -> Vec<RwLockReadGuard<HashMap<S, M>>> {
+        self.shards
+            .iter()
+            .map(|shard| RwLockReadGuard(shard.read()))
+            .collect()
     }
 }
 
 impl<S, M> Clone for Family<S, M> {
     fn clone(&self) -> Self {
         Family {
-            metrics: self.metrics.clone(),
-            constructor: self.constructor,
+            shards: self.shards.clone(),
+            constructor: self.constructor.clone(),
         }
     }
 }
@@ -203,10 +326,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn remove_and_clear() {
+        let family = Family::<Vec<(String, String)>, Counter<AtomicU64>>::default();
+        let labels = vec![("method".to_string(), "GET".to_string())];
+
+        assert!(family.get(&labels).is_none());
+
+        family.get_or_create(&labels).inc();
+        assert!(family.get(&labels).is_some());
+
+        assert!(family.remove(&labels));
+        assert!(!family.remove(&labels));
+        assert!(family.get(&labels).is_none());
+
+        family.get_or_create(&labels).inc();
+        family.clear();
+        assert!(family.get(&labels).is_none());
+    }
+
+    #[test]
+    fn metrics_spread_across_shards() {
+        let family = Family::<Vec<(String, String)>, Counter<AtomicU64>>::default();
+
+        for i in 0..1_000 {
+            family
+                .get_or_create(&vec![("i".to_string(), i.to_string())])
+                .inc();
+        }
+
+        let total: u64 = family
+            .read()
+            .iter()
+            .flat_map(|shard| shard.values())
+            .map(|counter| counter.get())
+            .sum();
+        assert_eq!(1_000, total);
+
+        let non_empty_shards = family
+            .read()
+            .iter()
+            .filter(|shard| !shard.is_empty())
+            .count();
+        assert!(non_empty_shards > 1);
+    }
+
     #[test]
     fn histogram_family() {
         Family::<(), Histogram>::new_with_constructor(|| {
             Histogram::new(exponential_series(1.0, 2.0, 10))
         });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn histogram_family_with_capturing_constructor() {
+        let start = 1.0;
+        let factor = 2.0;
+        let count = 10;
+
+        let family = Family::<(), Histogram>::new_with_constructor(move || {
+            Histogram::new(exponential_series(start, factor, count))
+        });
+
+        family.get_or_create(&());
+    }
+}
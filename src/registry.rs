@@ -0,0 +1,113 @@
+//! Open Metrics metric registry.
+//!
+//! See [`Registry`] for details.
+
+use crate::encoding::protobuf::EncodeMetric as ProtobufEncodeMetric;
+use crate::encoding::text::EncodeMetric as TextEncodeMetric;
+
+/// Metadata describing a registered metric family: its name and help text.
+pub struct Descriptor {
+    name: String,
+    help: String,
+}
+
+impl Descriptor {
+    fn new(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+        }
+    }
+
+    /// The metric family's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The metric family's help text.
+    pub fn help(&self) -> &str {
+        &self.help
+    }
+}
+
+/// A metric registry.
+///
+/// First register a metric family with a name and help text.
+///
+/// ```
+/// # use open_metrics_client::metrics::counter::{Atomic, Counter};
+/// # use open_metrics_client::registry::Registry;
+/// # use std::sync::atomic::AtomicU64;
+/// #
+/// let mut registry = Registry::default();
+/// let counter = Counter::<AtomicU64>::default();
+/// registry.register("my_counter", "This is my counter", counter.clone());
+/// ```
+///
+/// Then pass the registry to the text encoder,
+/// [`encoding::text::encode`](crate::encoding::text::encode).
+///
+/// Protobuf encoding is opt-in: a label type written by hand for the text
+/// encoder alone (see the "custom type for performance" example on
+/// [`Family`](crate::metrics::family::Family)) is under no obligation to
+/// also implement [`protobuf::EncodeLabelSet`](crate::encoding::protobuf::EncodeLabelSet).
+/// Call [`Registry::register_protobuf`] instead of [`Registry::register`]
+/// for metrics that implement both encoders and should be reachable from
+/// [`encoding::protobuf::encode`](crate::encoding::protobuf::encode) too.
+#[derive(Default)]
+pub struct Registry {
+    text_metrics: Vec<(Descriptor, Box<dyn TextEncodeMetric + Send + Sync>)>,
+    protobuf_metrics: Vec<(Descriptor, Box<dyn ProtobufEncodeMetric + Send + Sync>)>,
+}
+
+impl Registry {
+    /// Register a metric family under `name`, documented by `help`, for
+    /// text encoding.
+    pub fn register<N: Into<String>, H: Into<String>>(
+        &mut self,
+        name: N,
+        help: H,
+        metric: impl TextEncodeMetric + Send + Sync + 'static,
+    ) {
+        self.text_metrics
+            .push((Descriptor::new(name, help), Box::new(metric)));
+    }
+
+    /// Register a metric family under `name`, documented by `help`, for
+    /// both text and protobuf encoding.
+    ///
+    /// Unlike [`Registry::register`], this additionally requires `metric` to
+    /// implement the protobuf encoder (and to be cheaply [`Clone`], since it
+    /// is stored once per encoder).
+    pub fn register_protobuf<N, H, M>(&mut self, name: N, help: H, metric: M)
+    where
+        N: Into<String>,
+        H: Into<String>,
+        M: TextEncodeMetric + ProtobufEncodeMetric + Clone + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let help = help.into();
+        self.text_metrics.push((
+            Descriptor::new(name.clone(), help.clone()),
+            Box::new(metric.clone()),
+        ));
+        self.protobuf_metrics
+            .push((Descriptor::new(name, help), Box::new(metric)));
+    }
+
+    /// Iterate over the metric families registered for text encoding.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Descriptor, &dyn TextEncodeMetric)> {
+        self.text_metrics
+            .iter()
+            .map(|(descriptor, metric)| (descriptor, metric.as_ref()))
+    }
+
+    /// Iterate over the metric families registered for protobuf encoding.
+    pub(crate) fn iter_protobuf(
+        &self,
+    ) -> impl Iterator<Item = (&Descriptor, &dyn ProtobufEncodeMetric)> {
+        self.protobuf_metrics
+            .iter()
+            .map(|(descriptor, metric)| (descriptor, metric.as_ref()))
+    }
+}